@@ -0,0 +1,68 @@
+use secrecy::{ExposeSecret, SecretString};
+use std::path::PathBuf;
+
+use crate::secrets;
+
+/// The argv0 ssh/git is told to invoke; we multicall on it rather than
+/// shipping a second binary, same idea as e.g. busybox applets.
+const ASKPASS_EXE_NAME: &str = "giterdone-askpass";
+
+/// Entry point when the binary is invoked as `giterdone-askpass`. `args`
+/// is whatever SSH_ASKPASS/GIT_ASKPASS passed on the command line (the
+/// prompt text, e.g. "Enter passphrase for key '...'" or "Password for
+/// 'https://...'"). Never returns: prints the resolved credential to
+/// stdout and exits, which is all an askpass helper is expected to do.
+pub fn run(args: &[String]) -> ! {
+    let prompt = args.join(" ");
+    match resolve(&prompt) {
+        Ok(secret) => {
+            println!("{}", secret.expose_secret());
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("giterdone-askpass: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn resolve(prompt: &str) -> Result<SecretString, String> {
+    let passphrase = secrets::passphrase_from_env();
+    if prompt.to_lowercase().contains("passphrase") {
+        // An OpenSSH private-key passphrase prompt: reuse the master
+        // passphrase the user set for at-rest encryption, since that's
+        // the only secret we have the means to supply non-interactively.
+        passphrase.ok_or_else(|| {
+            format!("{} is not set; cannot answer an SSH key passphrase prompt", secrets::PASSPHRASE_ENV_VAR)
+        })
+    } else {
+        secrets::load_token(passphrase.as_ref())
+    }
+}
+
+/// Makes sure a `giterdone-askpass` symlink exists next to the running
+/// executable, so `SSH_ASKPASS`/`GIT_ASKPASS` can point ssh/git at
+/// something that, when invoked with just the prompt as an argument,
+/// resolves back into this binary's multicall dispatch in `main`.
+pub fn ensure_installed() -> Result<PathBuf, String> {
+    let current_exe = std::env::current_exe().map_err(|e| format!("Failed to get current executable path: {}", e))?;
+    let askpass_path = current_exe
+        .parent()
+        .ok_or_else(|| "Current executable has no parent directory".to_string())?
+        .join(ASKPASS_EXE_NAME);
+
+    if !askpass_path.exists() {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&current_exe, &askpass_path)
+                .map_err(|e| format!("Failed to create {:?}: {}", askpass_path, e))?;
+        }
+        #[cfg(not(unix))]
+        {
+            std::fs::copy(&current_exe, &askpass_path)
+                .map_err(|e| format!("Failed to create {:?}: {}", askpass_path, e))?;
+        }
+    }
+
+    Ok(askpass_path)
+}
@@ -13,11 +13,25 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Initializes the configuration wizard
-    Init,
+    Init {
+        /// Skip validating a personal access token against the provider's API; for offline installs
+        #[arg(long)]
+        skip_token_check: bool,
+    },
     /// Runs a backup immediately
-    RunNow,
+    RunNow {
+        /// Only back up this named group (may be repeated); defaults to all groups
+        #[arg(long)]
+        group: Vec<String>,
+    },
     /// Simulates a backup without committing or pushing
-    DryRun,
+    DryRun {
+        /// Only back up this named group (may be repeated); defaults to all groups
+        #[arg(long)]
+        group: Vec<String>,
+    },
     /// Shows the current configuration
     Status,
+    /// Watches the backed-up paths and runs a backup on filesystem changes
+    Watch,
 }
@@ -1,27 +1,121 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub const DEFAULT_GROUP: &str = "default";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub repo_url: String,
     pub auth: AuthMethod,
-    pub files_to_backup: Vec<PathBuf>,
+    #[serde(default)]
+    pub groups: HashMap<String, BackupGroup>,
+    /// Legacy flat path list from before named groups existed. Only ever
+    /// populated on deserialize of an old config; `load` folds it into
+    /// `groups[DEFAULT_GROUP]` and clears it, so `save` never writes it back out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_to_backup: Option<Vec<PathBuf>>,
     pub backup_schedule: String,
     pub commit_message_template: String,
     pub log_file: PathBuf,
+    #[serde(default)]
+    pub scheduler: Scheduler,
+    /// Whether the HTTPS token and SSH key are sealed with a master
+    /// passphrase (see the `secrets` module) rather than stored in plaintext.
+    #[serde(default)]
+    pub secrets_encrypted: bool,
+    /// Where to report a failed backup run. Empty by default, so installs
+    /// that never configure one keep today's silent-on-failure behavior.
+    #[serde(default)]
+    pub notify_sinks: Vec<NotifySink>,
+    /// The provider username behind the configured PAT, captured when the
+    /// token was validated at setup time; used as the commit author
+    /// identity in place of the generic "giterdone" fallback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_author_username: Option<String>,
+    /// How long `watch` mode waits for filesystem activity to go quiet
+    /// before triggering a backup. See `watcher::DEFAULT_DEBOUNCE_SECS`.
+    #[serde(default = "default_watch_debounce_secs")]
+    pub watch_debounce_secs: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+fn default_watch_debounce_secs() -> u64 {
+    crate::watcher::DEFAULT_DEBOUNCE_SECS
+}
+
+/// A destination the `notifier` module reports a failed backup run to.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifySink {
+    /// Sends a short subject line plus the tail of the log over SMTP.
+    Smtp {
+        host: String,
+        port: u16,
+        from: String,
+        recipients: Vec<String>,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// POSTs a JSON payload (status, repo_url, timestamp, error) to `url`.
+    Webhook { url: String },
+}
+
+/// Hand-rolled rather than derived so `password` never shows up in a
+/// `Commands::Status` dump of `Config` or in the "failed to send
+/// notification" log line in `notifier.rs` the way the rest of this struct
+/// does by default.
+impl std::fmt::Debug for NotifySink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifySink::Smtp { host, port, from, recipients, username, password } => f
+                .debug_struct("Smtp")
+                .field("host", host)
+                .field("port", port)
+                .field("from", from)
+                .field("recipients", recipients)
+                .field("username", username)
+                .field("password", &password.as_ref().map(|_| "<redacted>"))
+                .finish(),
+            NotifySink::Webhook { url } => f.debug_struct("Webhook").field("url", url).finish(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scheduler {
+    /// A crontab entry installed via the `crontab` binary.
+    #[default]
+    Cron,
+    /// A `giterdone.service` + `giterdone.timer` pair under the user's
+    /// systemd instance, for systems with no cron daemon.
+    Systemd,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct BackupGroup {
+    pub paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AuthMethod {
-    Ssh,
-    Pat(String),
+    /// Authenticate over SSH using the private key at `key_path` (RSA,
+    /// Ed25519, whatever OpenSSH format it's in) rather than assuming `id_rsa`.
+    Ssh { key_path: PathBuf },
+    /// Authenticate over HTTPS with a personal access token. The token
+    /// itself is never stored here; see `secrets::store_token`/`load_token`.
+    HttpsToken,
 }
 
 impl Config {
     pub fn load() -> Result<Self, std::io::Error> {
         let config_path = config_path()?;
         let config_str = std::fs::read_to_string(config_path)?;
-        let config: Config = serde_json::from_str(&config_str).unwrap();
+        let mut config: Config = serde_json::from_str(&config_str).unwrap();
+        config.migrate_flat_list();
         Ok(config)
     }
 
@@ -31,6 +125,35 @@ impl Config {
         std::fs::create_dir_all(config_path.parent().unwrap())?;
         std::fs::write(config_path, config_str)
     }
+
+    /// Folds an old flat `files_to_backup` list into a single `"default"`
+    /// group, so configs written before named groups existed keep working.
+    fn migrate_flat_list(&mut self) {
+        if self.groups.is_empty() {
+            if let Some(paths) = self.files_to_backup.take() {
+                self.groups.insert(DEFAULT_GROUP.to_string(), BackupGroup { paths, ignore: Vec::new() });
+            }
+        } else {
+            self.files_to_backup = None;
+        }
+    }
+
+    /// The groups to back up, filtered down to `selected` when non-empty.
+    /// Errors if `selected` names a group that doesn't exist, rather than
+    /// silently backing up nothing.
+    pub fn selected_groups<'a>(&'a self, selected: &[String]) -> Result<Vec<(&'a str, &'a BackupGroup)>, String> {
+        for name in selected {
+            if !self.groups.contains_key(name) {
+                return Err(format!("unknown backup group '{}'", name));
+            }
+        }
+
+        Ok(self.groups
+            .iter()
+            .filter(|(name, _)| selected.is_empty() || selected.iter().any(|s| s == *name))
+            .map(|(name, group)| (name.as_str(), group))
+            .collect())
+    }
 }
 
 fn config_path() -> Result<PathBuf, std::io::Error> {
@@ -1,7 +1,13 @@
-use std::process::{Command, Stdio};
+use git2::{Cred, CredentialType, ErrorCode, FetchOptions, PushOptions, RemoteCallbacks, Repository};
+use secrecy::ExposeSecret;
+use std::cell::Cell;
 use std::path::{Path, PathBuf};
-use crate::config::{Config, AuthMethod};
+
+use crate::config::{AuthMethod, Config};
 use crate::logger::Logger;
+use crate::repo_url::RepoUrl;
+use crate::secrets;
+use crate::ssh;
 
 pub fn ensure_repo(config: &Config, logger: &Logger) -> Result<(), String> {
     let repo_path = get_repo_path(&config.repo_url);
@@ -10,197 +16,322 @@ pub fn ensure_repo(config: &Config, logger: &Logger) -> Result<(), String> {
         clone_repo(config, &repo_path, logger)?;
     } else {
         logger.log("Local repository found, synchronizing with remote...").unwrap();
-        
-        let current_dir_command = |cmd: &mut Command| { cmd.current_dir(&repo_path); };
-
-        // Fetch latest changes from remote
-        let mut fetch_cmd = Command::new("git");
-        fetch_cmd.arg("fetch").arg("origin");
-        execute_git_command_with_dir(fetch_cmd, current_dir_command, "fetch", logger)?;
-
-        // Check if remote main branch exists
-        let remote_main_exists = Command::new("git")
-            .current_dir(&repo_path)
-            .arg("branch")
-            .arg("-r")
-            .output()
-            .map_err(|e| format!("Failed to check remote branches: {}", e))?;
-        
-        let remote_main_exists = String::from_utf8_lossy(&remote_main_exists.stdout).contains("origin/main");
+        let repo = Repository::open(&repo_path)
+            .map_err(|e| format!("Failed to open local repository: {}", e))?;
+
+        fetch(config, &repo, logger)?;
+
+        let remote_main_exists = repo
+            .find_branch("origin/main", git2::BranchType::Remote)
+            .is_ok();
 
         if remote_main_exists {
             logger.log("Remote 'main' branch found. Resetting local to remote...").unwrap();
-            let mut reset_cmd = Command::new("git");
-            reset_cmd.arg("reset").arg("--hard").arg("origin/main");
-            execute_git_command_with_dir(reset_cmd, current_dir_command, "reset --hard", logger)?;
+            reset_hard_to(&repo, "refs/remotes/origin/main")?;
         } else {
             logger.log("Remote 'main' branch not found. Ensuring local branch is pushed...").unwrap();
-            // Ensure local 'main' branch exists and is pushed as new upstream
-            let mut checkout_cmd = Command::new("git");
-            checkout_cmd.arg("checkout").arg("-b").arg("main");
-            execute_git_command_with_dir(checkout_cmd, current_dir_command, "checkout -b main", logger).ok(); // Create if not exists
-            
-            let mut push_u_cmd = Command::new("git");
-            push_u_cmd.arg("push").arg("-u").arg("origin").arg("main");
-            execute_git_command_with_dir(push_u_cmd, current_dir_command, "push -u origin main", logger)?;
+            ensure_local_branch(&repo, "main").ok();
+            push_branch(config, &repo, "main", logger)
+                .map_err(|e| format_push_error("refs/heads/main:refs/heads/main", &e))?;
         }
 
         // Ensure 'alt' branch exists locally, but don't reset it automatically
-        let mut checkout_alt_cmd = Command::new("git");
-        checkout_alt_cmd.arg("checkout").arg("-b").arg("alt");
-        execute_git_command_with_dir(checkout_alt_cmd, current_dir_command, "checkout -b alt", logger).ok(); // Create if not exists
-
+        ensure_local_branch(&repo, "alt").ok();
     }
     Ok(())
 }
 
 pub fn add_commit_push(config: &Config, message: &str, dry_run: bool, logger: &Logger) -> Result<(), String> {
     let repo_path = get_repo_path(&config.repo_url);
-    add(&repo_path, logger)?;
-    commit(message, &repo_path, dry_run, logger)?;
-    if !dry_run {
-        push(config, &repo_path, logger)?;
+    let repo = Repository::open(&repo_path)
+        .map_err(|e| format!("Failed to open local repository: {}", e))?;
+
+    add_all(&repo)?;
+    let committed = commit(config, &repo, message, dry_run, logger)?;
+    if !dry_run && committed {
+        push(config, &repo, logger)?;
     }
     Ok(())
 }
 
-fn get_repo_path(repo_url: &str) -> std::path::PathBuf {
-    // Heuristic to get a good local repo path from the URL
-    let repo_name = repo_url.split('/').last().unwrap_or("giterdone-backup");
-    let repo_name = repo_name.trim_end_matches(".git");
+fn get_repo_path(repo_url: &str) -> PathBuf {
+    let repo_name = RepoUrl::parse(repo_url)
+        .map(|parsed| parsed.name)
+        .unwrap_or_else(|_| "giterdone-backup".to_string());
     dirs::config_dir().unwrap().join("giterdone").join(repo_name)
 }
 
 fn clone_repo(config: &Config, path: &Path, logger: &Logger) -> Result<(), String> {
-    let mut command = Command::new("git");
-    command.arg("clone");
-
     let clone_url = match config.auth {
-        AuthMethod::Ssh => convert_https_to_ssh(&config.repo_url),
+        AuthMethod::Ssh { .. } => convert_https_to_ssh(&config.repo_url)?,
+        AuthMethod::HttpsToken => RepoUrl::parse(&config.repo_url)?.to_https_url(),
     };
-    command.arg(clone_url).arg(path);
-    
-    // Set GIT_SSH_COMMAND if a custom key path was provided (e.g., id_rsa)
-    if let Some(ssh_key_path) = get_ssh_key_path() {
-        command.env("GIT_SSH_COMMAND", format!("ssh -i {}", ssh_key_path.display()));
-    }
 
-    execute_git_command(command, "clone", logger)
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks(config, logger));
+
+    logger.log("Executing git clone").unwrap();
+    git2::build::RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(&clone_url, path)
+        .map_err(|e| format!("git clone failed: {}", e))?;
+    logger.log("git clone successful").unwrap();
+    Ok(())
 }
 
-fn validate_remote(config: &Config, path: &Path, _logger: &Logger) -> Result<(), String> {
-    let mut command = Command::new("git");
-    command.current_dir(path).arg("remote").arg("-v");
-    let output = command.output().map_err(|e| format!("Failed to execute git remote: {}", e))?;
-    let remote_output = String::from_utf8_lossy(&output.stdout);
+fn validate_remote(config: &Config, repo: &Repository, _logger: &Logger) -> Result<(), String> {
+    let remote = repo
+        .find_remote("origin")
+        .map_err(|e| format!("Failed to find remote 'origin': {}", e))?;
+    let remote_url = remote.url().unwrap_or_default();
 
     let expected_url = match config.auth {
-        AuthMethod::Ssh => convert_https_to_ssh(&config.repo_url),
+        AuthMethod::Ssh { .. } => convert_https_to_ssh(&config.repo_url)?,
+        AuthMethod::HttpsToken => RepoUrl::parse(&config.repo_url)?.to_https_url(),
     };
 
-    if !remote_output.contains(&expected_url) {
-        return Err(format!("Remote URL mismatch. Expected: {}, Found: {}", expected_url, remote_output));
+    if remote_url != expected_url {
+        return Err(format!("Remote URL mismatch. Expected: {}, Found: {}", expected_url, remote_url));
     }
     Ok(())
 }
 
-fn add(path: &Path, logger: &Logger) -> Result<(), String> {
-    let mut command = Command::new("git");
-    command.current_dir(path).arg("add").arg(".");
-    execute_git_command(command, "add", logger)
+fn fetch(config: &Config, repo: &Repository, logger: &Logger) -> Result<(), String> {
+    let mut remote = repo
+        .find_remote("origin")
+        .map_err(|e| format!("Failed to find remote 'origin': {}", e))?;
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks(config, logger));
+
+    logger.log("Executing git fetch").unwrap();
+    remote
+        .fetch(&["refs/heads/*:refs/remotes/origin/*"], Some(&mut fetch_options), None)
+        .map_err(|e| format!("git fetch failed: {}", e))?;
+    logger.log("git fetch successful").unwrap();
+    Ok(())
+}
+
+fn reset_hard_to(repo: &Repository, refname: &str) -> Result<(), String> {
+    let obj = repo
+        .revparse_single(refname)
+        .map_err(|e| format!("Failed to resolve {}: {}", refname, e))?;
+    repo.reset(&obj, git2::ResetType::Hard, None)
+        .map_err(|e| format!("git reset --hard failed: {}", e))?;
+    Ok(())
 }
 
-fn commit(message: &str, path: &Path, dry_run: bool, logger: &Logger) -> Result<(), String> {
-    let mut command = Command::new("git");
-    command.current_dir(path).arg("commit").arg("-m").arg(message);
-    if dry_run {
-        command.arg("--dry-run");
+fn ensure_local_branch(repo: &Repository, name: &str) -> Result<(), String> {
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        return Ok(());
     }
-    let result = execute_git_command(command, "commit", logger);
+    let head_commit = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(|e| format!("Failed to resolve HEAD commit: {}", e))?;
+    repo.branch(name, &head_commit, false)
+        .map_err(|e| format!("Failed to create branch '{}': {}", name, e))?;
+    Ok(())
+}
+
+fn add_all(repo: &Repository) -> Result<(), String> {
+    let mut index = repo.index().map_err(|e| format!("Failed to open index: {}", e))?;
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .map_err(|e| format!("git add failed: {}", e))?;
+    index.write().map_err(|e| format!("Failed to write index: {}", e))?;
+    Ok(())
+}
+
+/// Creates a commit from the current index. Returns `Ok(false)` instead of an
+/// error when there is nothing to commit, mirroring git's own "nothing to
+/// commit" behavior without needing to inspect stderr text.
+fn commit(config: &Config, repo: &Repository, message: &str, dry_run: bool, logger: &Logger) -> Result<bool, String> {
+    let mut index = repo.index().map_err(|e| format!("Failed to open index: {}", e))?;
+    let tree_id = index.write_tree().map_err(|e| format!("Failed to write tree: {}", e))?;
+    let tree = repo.find_tree(tree_id).map_err(|e| format!("Failed to find tree: {}", e))?;
 
-    if let Err(e) = &result {
-        if e.contains("nothing to commit") || e.contains("no changes added to commit") {
+    let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    if let Some(parent) = &head {
+        if parent.tree_id() == tree_id {
             logger.log("No changes to commit. Treating as successful.").unwrap();
-            return Ok(());
+            return Ok(false);
         }
     }
-    result
-}
 
-fn push(_config: &Config, path: &Path, logger: &Logger) -> Result<(), String> {
-    let mut command = Command::new("git");
-    command.current_dir(path);
-    command.arg("push").arg("origin").arg("main");
-
-    // Set GIT_SSH_COMMAND if a custom key path was provided (e.g., id_rsa)
-    if let Some(ssh_key_path) = get_ssh_key_path() {
-        command.env("GIT_SSH_COMMAND", format!("ssh -i {}", ssh_key_path.display()));
+    if dry_run {
+        logger.log("Dry run: would have committed changes.").unwrap();
+        return Ok(true);
     }
 
-    let result = execute_git_command(command, "push to main", logger);
+    let signature = repo
+        .signature()
+        .or_else(|_| match &config.commit_author_username {
+            Some(username) => git2::Signature::now(username, &format!("{}@users.noreply.github.com", username)),
+            None => git2::Signature::now("giterdone", "giterdone@localhost"),
+        })
+        .map_err(|e| format!("Failed to build commit signature: {}", e))?;
 
-    if let Err(e) = &result {
-        if e.contains("rejected") || e.contains("fetch first") {
-            logger.log("Push to 'main' rejected due to divergent history. Attempting push to 'alt'...").unwrap();
-            let mut alt_command = Command::new("git");
-            alt_command.current_dir(path);
-            alt_command.arg("push").arg("origin").arg("alt");
-            if let Some(ssh_key_path) = get_ssh_key_path() {
-                alt_command.env("GIT_SSH_COMMAND", format!("ssh -i {}", ssh_key_path.display()));
-            }
-            let alt_result = execute_git_command(alt_command, "push to alt", logger);
+    let parents: Vec<&git2::Commit> = head.as_ref().into_iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .map_err(|e| format!("git commit failed: {}", e))?;
 
-            if let Err(e_alt) = &alt_result {
-                if e_alt.contains("rejected") || e_alt.contains("fetch first") {
+    logger.log("git commit successful").unwrap();
+    Ok(true)
+}
+
+fn push(config: &Config, repo: &Repository, logger: &Logger) -> Result<(), String> {
+    match push_branch(config, repo, "main", logger) {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == ErrorCode::NotFastForward => {
+            logger.log("Push to 'main' rejected due to divergent history. Attempting push to 'alt'...").unwrap();
+            match push_branch(config, repo, "alt", logger) {
+                Ok(()) => Ok(()),
+                Err(e_alt) if e_alt.code() == ErrorCode::NotFastForward => {
                     logger.log("Push to 'alt' also rejected. Attempting force push to 'alt'...").unwrap();
-                    let mut force_alt_command = Command::new("git");
-                    force_alt_command.current_dir(path);
-                    force_alt_command.arg("push").arg("--force").arg("origin").arg("alt");
-                    if let Some(ssh_key_path) = get_ssh_key_path() {
-                        force_alt_command.env("GIT_SSH_COMMAND", format!("ssh -i {}", ssh_key_path.display()));
-                    }
-                    return execute_git_command(force_alt_command, "force push to alt", logger);
+                    force_push_branch(config, repo, "alt", logger)
+                        .map_err(|e| format_push_error("+refs/heads/alt:refs/heads/alt", &e))
                 }
+                Err(e_alt) => Err(format_push_error("refs/heads/alt:refs/heads/alt", &e_alt)),
             }
-            return alt_result;
         }
+        Err(e) => Err(format_push_error("refs/heads/main:refs/heads/main", &e)),
     }
-    result
 }
 
-fn execute_git_command(mut command: Command, operation: &str, logger: &Logger) -> Result<(), String> {
-    logger.log(&format!("Executing git {}", operation)).unwrap();
-    let status = command.stdout(Stdio::piped()).stderr(Stdio::piped()).status()
-        .map_err(|e| format!("Failed to execute git {}: {}", operation, e))?;
+fn push_branch(config: &Config, repo: &Repository, branch: &str, logger: &Logger) -> Result<(), git2::Error> {
+    do_push(config, repo, &format!("refs/heads/{0}:refs/heads/{0}", branch), logger)
+}
+
+fn force_push_branch(config: &Config, repo: &Repository, branch: &str, logger: &Logger) -> Result<(), git2::Error> {
+    do_push(config, repo, &format!("+refs/heads/{0}:refs/heads/{0}", branch), logger)
+}
+
+/// Returns the raw `git2::Error` rather than a formatted `String` so
+/// `push()` can branch on `err.code()` directly instead of pattern-matching
+/// text; only the callers that hand it back as a `String` (`push`,
+/// `ensure_repo`) format it.
+fn do_push(config: &Config, repo: &Repository, refspec: &str, logger: &Logger) -> Result<(), git2::Error> {
+    let mut remote = repo.find_remote("origin")?;
+
+    let mut push_options = PushOptions::new();
+    push_options.remote_callbacks(remote_callbacks(config, logger));
 
-    if !status.success() {
-        let output = command.output().unwrap();
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let error_message = format!("git {} failed: {}\n{}", operation, status, stderr);
-        logger.log(&error_message).unwrap();
-        return Err(error_message);
+    logger.log(&format!("Executing git push {}", refspec)).unwrap();
+    let result = remote.push(&[refspec], Some(&mut push_options));
+
+    match &result {
+        Ok(()) => logger.log(&format!("git push {} successful", refspec)).unwrap(),
+        Err(e) => logger.log(&format_push_error(refspec, e)).unwrap(),
     }
-    logger.log(&format!("git {} successful", operation)).unwrap();
-    Ok(())
+    result
 }
 
-// Helper function to execute git commands with a custom directory closure
-fn execute_git_command_with_dir<F>(command: Command, dir_setter: F, operation: &str, logger: &Logger) -> Result<(), String>
-where
-    F: FnOnce(&mut Command),
-{
-    let mut cmd = command;
-    dir_setter(&mut cmd);
-    execute_git_command(cmd, operation, logger)
+fn format_push_error(refspec: &str, err: &git2::Error) -> String {
+    format!("git push {} failed: {}", refspec, err)
 }
 
-fn convert_https_to_ssh(https_url: &str) -> String {
-    https_url
-        .replace("https://github.com/", "git@github.com:")
-        .replace(".git", "") // Remove .git if present, as SSH URLs often omit it
+/// Builds the credential-callback cascade used for both fetch and push.
+///
+/// libgit2 re-invokes `.credentials()` on every authentication failure, so a
+/// flag is captured per credential method to make sure each method is only
+/// tried once before giving up, rather than looping forever against a
+/// rejecting remote.
+fn remote_callbacks(config: &Config, logger: &Logger) -> RemoteCallbacks<'static> {
+    let tried_agent = Cell::new(false);
+    let tried_key_file = Cell::new(false);
+    let tried_token = Cell::new(false);
+    let is_token_auth = config.auth == AuthMethod::HttpsToken;
+    let secrets_encrypted = config.secrets_encrypted;
+    let key_path = match &config.auth {
+        AuthMethod::Ssh { key_path } => Some(key_path.clone()),
+        AuthMethod::HttpsToken => None,
+    };
+
+    // Resolved once per git operation (clone/fetch/push), not once per
+    // credential attempt, so an interactive run only prompts once even
+    // though libgit2 re-invokes `.credentials()` for every method it tries.
+    let passphrase = if secrets_encrypted { secrets::resolve_passphrase(logger) } else { None };
+    let passphrase_missing = secrets_encrypted && passphrase.is_none();
+
+    let mut callbacks = RemoteCallbacks::new();
+    // `remote.push()` only reports a transport-level failure through its own
+    // `Result`; a per-ref rejection (e.g. non-fast-forward) is reported here
+    // instead, as free text, so it has to be turned into an error explicitly
+    // or it's silently swallowed and the push looks like it succeeded. libgit2
+    // doesn't hand us a structured reason, so this is the one place that has
+    // to sniff the message text — but it does so to classify the error with
+    // the right `ErrorCode`, so every caller downstream (`push`'s
+    // main->alt->force-push fallback) can match on `err.code()` instead of
+    // re-sniffing a formatted string itself.
+    callbacks.push_update_reference(|_refname, status| match status {
+        None => Ok(()),
+        Some(status) => {
+            let code = if status.contains("non-fast-forward") {
+                ErrorCode::NotFastForward
+            } else {
+                ErrorCode::GenericError
+            };
+            Err(git2::Error::new(code, git2::ErrorClass::Reference, status))
+        }
+    });
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            if !tried_agent.get() {
+                tried_agent.set(true);
+                if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+            if !tried_key_file.get() {
+                tried_key_file.set(true);
+                if let Some(private_key) = &key_path {
+                    let public_key = private_key.with_extension("pub");
+
+                    if secrets_encrypted {
+                        if let Some(passphrase) = &passphrase {
+                            if let Ok(key_content) = ssh::load_ssh_key_content(private_key, Some(passphrase)) {
+                                if let Ok(cred) = Cred::ssh_key_from_memory(username, None, &key_content, None) {
+                                    return Ok(cred);
+                                }
+                            }
+                        }
+                    } else if let Ok(cred) = Cred::ssh_key(username, Some(&public_key), private_key, None) {
+                        return Ok(cred);
+                    }
+                }
+            }
+        }
+
+        if is_token_auth && allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried_token.get() {
+            tried_token.set(true);
+            if !secrets_encrypted || passphrase.is_some() {
+                if let Ok(token) = secrets::load_token(passphrase.as_ref()) {
+                    if let Ok(cred) = Cred::userpass_plaintext(username, token.expose_secret()) {
+                        return Ok(cred);
+                    }
+                }
+            }
+        }
+
+        if passphrase_missing {
+            Err(git2::Error::from_str(&format!(
+                "stored credentials for {} are encrypted and no master passphrase was available (set {} or run interactively)",
+                url, secrets::PASSPHRASE_ENV_VAR
+            )))
+        } else {
+            Err(git2::Error::from_str(&format!(
+                "exhausted configured credential methods for {}",
+                url
+            )))
+        }
+    });
+
+    callbacks
 }
 
-// Helper to get the default SSH key path
-fn get_ssh_key_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|home| home.join(".ssh").join("id_rsa"))
-}
\ No newline at end of file
+fn convert_https_to_ssh(repo_url: &str) -> Result<String, String> {
+    Ok(RepoUrl::parse(repo_url)?.to_ssh_url())
+}
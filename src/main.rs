@@ -1,66 +1,116 @@
+mod askpass;
 mod cli;
 mod config;
 mod git;
 mod logger;
+mod notifier;
+mod provider;
+mod repo_url;
 mod scanner;
 mod scheduler;
+mod secrets;
 mod ssh;
+mod watcher;
 
 use crate::logger::Logger;
 use chrono::Local;
 use clap::Parser;
 use cli::{Cli, Commands};
-use config::{AuthMethod, Config};
+use config::{AuthMethod, BackupGroup, Config, Scheduler};
+use repo_url::RepoUrl;
+use secrecy::SecretString;
+use std::collections::HashMap;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::fs;
 
 fn main() {
+    // Multicall dispatch: when invoked as `giterdone-askpass` (the name
+    // SSH_ASKPASS/GIT_ASKPASS are pointed at), skip the normal CLI
+    // entirely and answer the credential prompt on argv instead.
+    let raw_args: Vec<String> = std::env::args().collect();
+    if Path::new(&raw_args[0]).file_name().and_then(|n| n.to_str()) == Some("giterdone-askpass") {
+        askpass::run(&raw_args[1..]);
+    }
+
     let cli = Cli::parse();
     let logger = Logger::new().expect("Failed to initialize logger");
 
     match &cli.command {
-        Some(Commands::Init) => {
+        Some(Commands::Init { skip_token_check }) => {
             println!("Running setup wizard...");
-            if let Err(e) = setup_wizard(&logger) {
+            if let Err(e) = setup_wizard(&logger, *skip_token_check) {
                 eprintln!("Setup failed: {}", e);
                 logger.log(&format!("Setup failed: {}", e)).unwrap();
             }
         }
-        Some(Commands::RunNow) => {
+        Some(Commands::RunNow { group }) => {
             println!("Running immediate backup...");
-            run_backup(false, &logger);
+            run_backup(false, &logger, group);
         }
-        Some(Commands::DryRun) => {
+        Some(Commands::DryRun { group }) => {
             println!("Performing a dry run...");
-            run_backup(true, &logger);
+            run_backup(true, &logger, group);
         }
         Some(Commands::Status) => {
             match Config::load() {
-                Ok(config) => println!("Current configuration:
-{:#?}", config),
+                Ok(config) => {
+                    println!("Current configuration:
+{:#?}", config);
+                    println!("\nGroups:");
+                    for (name, group) in &config.groups {
+                        println!("  {} ({} path(s)):", name, group.paths.len());
+                        for path in &group.paths {
+                            println!("    {}", path.display());
+                        }
+                    }
+                }
                 Err(_) => println!("Configuration file not found. Run 'giterdone init' to set up."),
             }
         }
+        Some(Commands::Watch) => {
+            let config = match Config::load() {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Failed to load config: {}. Run 'giterdone init'.", e);
+                    return;
+                }
+            };
+            let groups = match config.selected_groups(&[]) {
+                Ok(g) => g,
+                Err(e) => {
+                    eprintln!("Invalid group configuration: {}", e);
+                    return;
+                }
+            };
+            let path_count: usize = groups.iter().map(|(_, g)| g.paths.len()).sum();
+            let debounce = std::time::Duration::from_secs(config.watch_debounce_secs);
+            println!("Watching {} path(s) for changes. Press Ctrl+C to stop.", path_count);
+            logger.log("Starting watch mode...").unwrap();
+            if let Err(e) = watcher::watch(&groups, debounce, || run_backup(false, &logger, &[])) {
+                eprintln!("Watcher failed: {}", e);
+                logger.log(&format!("Watcher failed: {}", e)).unwrap();
+            }
+        }
         None => {
             // Default action: run backup if config exists
             if Config::load().is_ok() {
-                run_backup(false, &logger);
+                run_backup(false, &logger, &[]);
             } else {
                 println!("Configuration not found. Running setup wizard...");
-                if let Err(e) = setup_wizard(&logger) {
+                if let Err(e) = setup_wizard(&logger, false) {
                     eprintln!("Setup failed: {}", e);
                     logger.log(&format!("Setup failed: {}", e)).unwrap();
                 } else {
                     // Run a backup immediately after setup
-                    run_backup(false, &logger);
+                    run_backup(false, &logger, &[]);
                 }
             }
         }
     }
 }
 
-fn run_backup(dry_run: bool, logger: &Logger) {
+fn run_backup(dry_run: bool, logger: &Logger, groups: &[String]) {
     logger.log("Starting backup process...").unwrap();
     let config = match Config::load() {
         Ok(c) => c,
@@ -72,41 +122,58 @@ fn run_backup(dry_run: bool, logger: &Logger) {
         }
     };
 
-    // Ensure SSH setup is complete before proceeding with Git operations
-    if let Err(e) = ensure_ssh_setup(logger) {
-        let msg = format!("SSH setup failed: {}", e);
+    let report_failure = |msg: &str| {
         eprintln!("{}", msg);
-        logger.log(&msg).unwrap();
-        return;
+        logger.log(msg).unwrap();
+        notifier::notify_failure(&config.notify_sinks, &config.repo_url, msg, &config.log_file, logger);
+    };
+
+    // SSH setup only applies in SSH auth mode; token auth has nothing to check here.
+    if let AuthMethod::Ssh { key_path } = &config.auth {
+        let passphrase = if config.secrets_encrypted { secrets::passphrase_from_env() } else { None };
+        if let Err(e) = ensure_ssh_setup(key_path, &config.repo_url, passphrase.as_ref(), logger) {
+            report_failure(&format!("SSH setup failed: {}", e));
+            return;
+        }
+    }
+
+    // Cron/systemd runs have no controlling tty, so a passphrase-protected
+    // key or a PAT that needs entering would otherwise hang. Point ssh/git
+    // at our askpass helper and force it to be consulted instead.
+    if let Err(e) = setup_askpass_env() {
+        logger.log(&format!("Warning: failed to configure askpass helper: {}", e)).unwrap();
     }
 
     if let Err(e) = git::ensure_repo(&config, logger) {
-        let msg = format!("Git repository validation failed: {}", e);
-        eprintln!("{}", msg);
-        logger.log(&msg).unwrap();
+        report_failure(&format!("Git repository validation failed: {}", e));
         return;
     }
 
     // The local path where the git repo is cloned
     let repo_base_path = get_repo_local_path(&config.repo_url);
 
-    // 1. Scan for files and generate .gitignore content
-    let (files_to_backup, gitignore_content) = scanner::scan(&config.files_to_backup);
-    
+    // 1. Scan the selected groups and generate .gitignore content
+    let selected_groups = match config.selected_groups(groups) {
+        Ok(g) => g,
+        Err(e) => {
+            report_failure(&format!("Invalid --group selection: {}", e));
+            return;
+        }
+    };
+    let (files_to_backup, gitignore_content) = scanner::scan(&selected_groups);
+
     // 2. Write the .gitignore file
     let gitignore_path = repo_base_path.join(".gitignore");
     if let Err(e) = fs::write(&gitignore_path, gitignore_content) {
-        let msg = format!("Failed to write .gitignore: {}", e);
-        eprintln!("{}", msg);
-        logger.log(&msg).unwrap();
+        report_failure(&format!("Failed to write .gitignore: {}", e));
         return;
     }
     logger.log(&format!(".gitignore file written to {:?}", gitignore_path)).unwrap();
 
     // 3. Copy the discovered files to the local git repo, preserving structure
-    for (source_path, relative_dest_path) in &files_to_backup {
-        if let Err(e) = copy_file_to_repo(source_path, &repo_base_path, relative_dest_path) {
-            let msg = format!("Failed to copy file {:?}: {}", source_path, e);
+    for file in &files_to_backup {
+        if let Err(e) = copy_file_to_repo(&file.source_path, &repo_base_path, &file.relative_path) {
+            let msg = format!("Failed to copy file {:?} (group '{}'): {}", file.source_path, file.group, e);
             eprintln!("{}", msg);
             logger.log(&msg).unwrap();
         }
@@ -121,25 +188,63 @@ fn run_backup(dry_run: bool, logger: &Logger) {
             println!("{}", msg);
             logger.log(msg).unwrap();
         }
-        Err(e) => {
-            let msg = format!("Backup process failed: {}", e);
-            eprintln!("{}", msg);
-            logger.log(&msg).unwrap();
-        }
+        Err(e) => report_failure(&format!("Backup process failed: {}", e)),
     }
 }
 
-fn setup_wizard(logger: &Logger) -> Result<(), String> {
+fn setup_askpass_env() -> Result<(), String> {
+    let askpass_path = askpass::ensure_installed()?;
+    std::env::set_var("SSH_ASKPASS", &askpass_path);
+    std::env::set_var("SSH_ASKPASS_REQUIRE", "force");
+    std::env::set_var("GIT_ASKPASS", &askpass_path);
+    Ok(())
+}
+
+fn setup_wizard(logger: &Logger, skip_token_check: bool) -> Result<(), String> {
     println!("Welcome to giterdone setup!");
 
+    // Loaded before the config is overwritten below, so a re-run that
+    // switches scheduler backends can tear down the old one instead of
+    // leaving it installed alongside the new one.
+    let previous_scheduler = Config::load().ok().map(|c| c.scheduler);
+
     let repo_url = prompt("Enter the remote GitHub repository URL (e.g., https://github.com/user/repo.git):")?;
-    let auth = AuthMethod::Ssh;
+    RepoUrl::parse(&repo_url)?;
+
+    let auth_choice = prompt("Authenticate via 'ssh' or 'token' (HTTPS personal access token)? [ssh]:")?;
+    let use_ssh = !matches!(auth_choice.to_lowercase().as_str(), "token" | "pat" | "https");
+
+    let encrypt_secrets = prompt_bool("Encrypt the stored SSH key / token at rest with a master passphrase? (y/n)")?;
+    let passphrase = if encrypt_secrets {
+        Some(SecretString::from(prompt("Enter a master passphrase:")?))
+    } else {
+        None
+    };
+
+    let mut commit_author_username = None;
+    let auth = if use_ssh {
+        let key_path = ssh::default_ssh_key_path()?;
+        ensure_ssh_setup(&key_path, &repo_url, passphrase.as_ref(), logger)?;
+        AuthMethod::Ssh { key_path }
+    } else {
+        let token = SecretString::from(prompt("Enter your HTTPS personal access token:")?);
+
+        if skip_token_check {
+            println!("Skipping token validation (--skip-token-check).");
+        } else {
+            let username = provider::verify_token(&repo_url, &token)
+                .map_err(|e| format!("Token validation failed: {} (rerun with --skip-token-check to bypass)", e))?;
+            println!("Token validated; authenticated as '{}'.", username);
+            commit_author_username = Some(username);
+        }
 
-    // Ensure SSH setup is complete during initial wizard as well
-    ensure_ssh_setup(logger)?;
+        secrets::store_token(&token, passphrase.as_ref())
+            .map_err(|e| format!("Failed to store personal access token: {}", e))?;
+        AuthMethod::HttpsToken
+    };
 
     let files_str = prompt("Enter files or directories to back up (comma-separated absolute paths):")?;
-    let files_to_backup: Vec<PathBuf> = files_str.split(',').map(|s| PathBuf::from(s.trim())).collect();
+    let paths: Vec<PathBuf> = files_str.split(',').map(|s| PathBuf::from(s.trim())).collect();
 
     let backup_schedule = prompt("Enter backup schedule (e.g., '0 * * * *' for hourly, '@daily', etc.):")?;
     let commit_message_template = prompt("Enter commit message template (e.g., 'Backup on %Y-%m-%d %H:%M:%S'):")?;
@@ -148,13 +253,28 @@ fn setup_wizard(logger: &Logger) -> Result<(), String> {
     fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log dir: {}", e))?;
     let log_file = log_dir.join("giterdone.log");
 
+    let scheduler_choice = prompt("Schedule backups via 'cron' or 'systemd' timers? [cron]:")?;
+    let scheduler = match scheduler_choice.to_lowercase().as_str() {
+        "systemd" => Scheduler::Systemd,
+        _ => Scheduler::Cron,
+    };
+
+    let mut groups = HashMap::new();
+    groups.insert(config::DEFAULT_GROUP.to_string(), BackupGroup { paths, ignore: Vec::new() });
+
     let config = Config {
         repo_url,
         auth,
-        files_to_backup,
+        groups,
+        files_to_backup: None,
         backup_schedule: backup_schedule.clone(),
         commit_message_template,
         log_file,
+        scheduler,
+        secrets_encrypted: encrypt_secrets,
+        notify_sinks: Vec::new(),
+        commit_author_username,
+        watch_debounce_secs: watcher::DEFAULT_DEBOUNCE_SECS,
     };
 
     // Save config
@@ -162,9 +282,17 @@ fn setup_wizard(logger: &Logger) -> Result<(), String> {
     println!("Configuration saved successfully.");
     logger.log("Configuration saved.").unwrap();
 
-    // Setup cron job
-    scheduler::setup_cron_job(&config.backup_schedule, logger)?;
-    println!("Cron job scheduled successfully.");
+    // Tear down the previous scheduler backend, if any, before installing
+    // the new one so switching Cron <-> Systemd doesn't leave both active.
+    if let Some(previous_scheduler) = previous_scheduler {
+        if let Err(e) = scheduler::uninstall(previous_scheduler, logger) {
+            logger.log(&format!("Warning: failed to remove previous scheduler: {}", e)).unwrap();
+        }
+    }
+
+    // Set up the scheduled backup
+    scheduler::install(config.scheduler, &config.backup_schedule, logger)?;
+    println!("Scheduled backup installed successfully.");
 
     // Initial clone
     git::ensure_repo(&config, logger)?;
@@ -173,47 +301,32 @@ fn setup_wizard(logger: &Logger) -> Result<(), String> {
     Ok(())
 }
 
-fn ensure_ssh_setup(logger: &Logger) -> Result<(), String> {
-    let ssh_key_path = dirs::home_dir().map(|home| home.join(".ssh").join("id_rsa"));
-    let known_hosts_path = dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts"));
-
-    let mut key_exists = false;
-    if let Some(path) = &ssh_key_path {
-        if path.exists() {
-            key_exists = true;
-        }
-    }
-
-    let mut github_known = false;
-    if let Some(path) = &known_hosts_path {
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(path) {
-                if content.contains("github.com") {
-                    github_known = true;
-                }
-            }
-        }
-    }
-
-    if !key_exists {
-        println!("\nSSH private key (~/.ssh/id_rsa) not found.");
-        let setup_ssh = prompt_bool("Do you want to provide your SSH private key now? (y/n)")?;
-        if setup_ssh {
-            println!("Paste your SSH private key (e.g., content of ~/.ssh/id_rsa). Press Enter twice when done:");
-            let key_content = read_multiline_input()?;
-            ssh::setup_ssh_key(&key_content, logger)?;
+fn ensure_ssh_setup(key_path: &Path, repo_url: &str, passphrase: Option<&SecretString>, logger: &Logger) -> Result<(), String> {
+    if !key_path.exists() {
+        println!("\nSSH private key ({:?}) not found.", key_path);
+        let generate = prompt_bool("Generate a new Ed25519 keypair now? (y/n, 'n' to paste an existing key instead)")?;
+        if generate {
+            let public_key = ssh::generate_ed25519_key(key_path, passphrase, logger)?;
+            println!("\nGenerated a new SSH key. Add this public key as a deploy key on your repo:\n{}\n", public_key);
         } else {
-            return Err("SSH key not provided. Cannot proceed with Git operations.".to_string());
+            let setup_ssh = prompt_bool("Do you want to provide your SSH private key now? (y/n)")?;
+            if setup_ssh {
+                println!("Paste your SSH private key. Press Enter twice when done:");
+                let key_content = read_multiline_input()?;
+                ssh::setup_ssh_key(key_path, &key_content, passphrase, logger)?;
+            } else {
+                return Err("SSH key not provided. Cannot proceed with Git operations.".to_string());
+            }
         }
     }
 
-    if !github_known {
-        println!("\nGitHub's host key not found in ~/.ssh/known_hosts.");
-        let add_host = prompt_bool("Do you want to add github.com to known_hosts now? (y/n)")?;
+    if !ssh::is_known_host_for_repo(repo_url) {
+        println!("\nThe remote's SSH host key was not found in known_hosts.");
+        let add_host = prompt_bool("Do you want to scan and add it now? (y/n)")?;
         if add_host {
-            ssh::add_github_to_known_hosts(logger)?;
+            ssh::add_known_host_for_repo(repo_url, logger)?;
         } else {
-            return Err("GitHub's host key not added to known_hosts. Cannot proceed with Git operations.".to_string());
+            return Err("Remote host key not added to known_hosts. Cannot proceed with Git operations.".to_string());
         }
     }
 
@@ -253,7 +366,9 @@ fn read_multiline_input() -> Result<String, String> {
 }
 
 fn get_repo_local_path(repo_url: &str) -> PathBuf {
-    let repo_name = repo_url.split('/').last().unwrap_or("giterdone-backup").trim_end_matches(".git");
+    let repo_name = RepoUrl::parse(repo_url)
+        .map(|parsed| parsed.name)
+        .unwrap_or_else(|_| "giterdone-backup".to_string());
     dirs::config_dir().unwrap().join("giterdone").join(repo_name)
 }
 
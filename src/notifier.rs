@@ -0,0 +1,107 @@
+use chrono::Local;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config::NotifySink;
+use crate::logger::Logger;
+
+/// How many trailing lines of the log file to include in an SMTP
+/// notification, enough to see what led up to the failure without
+/// attaching the whole thing.
+const LOG_TAIL_LINES: usize = 20;
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    status: &'a str,
+    repo_url: &'a str,
+    timestamp: String,
+    error: &'a str,
+}
+
+/// Reports a failed backup run through every configured sink. Sinks are
+/// best-effort and independent of each other: a failure sending to one
+/// doesn't stop the others, it's just logged and swallowed, since the
+/// backup has already failed and this is only the notification about it.
+pub fn notify_failure(sinks: &[NotifySink], repo_url: &str, error: &str, log_file: &Path, logger: &Logger) {
+    for sink in sinks {
+        let result = match sink {
+            NotifySink::Smtp { host, port, from, recipients, username, password } => send_smtp(
+                host,
+                *port,
+                from,
+                recipients,
+                username.as_deref(),
+                password.as_deref(),
+                repo_url,
+                error,
+                log_file,
+            ),
+            NotifySink::Webhook { url } => send_webhook(url, repo_url, error),
+        };
+        if let Err(e) = result {
+            logger.log(&format!("Failed to send failure notification via {:?}: {}", sink, e)).unwrap();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_smtp(
+    host: &str,
+    port: u16,
+    from: &str,
+    recipients: &[String],
+    username: Option<&str>,
+    password: Option<&str>,
+    repo_url: &str,
+    error: &str,
+    log_file: &Path,
+) -> Result<(), String> {
+    let tail = tail_of_log(log_file, LOG_TAIL_LINES);
+    let subject = format!("giterdone backup failed for {}", repo_url);
+    let body = format!("{}\n\n--- log tail ---\n{}", error, tail);
+
+    let from_mailbox: Mailbox = from.parse().map_err(|e| format!("Invalid From address '{}': {}", from, e))?;
+    let mut builder = Message::builder().from(from_mailbox).subject(subject);
+    for recipient in recipients {
+        let to: Mailbox = recipient.parse().map_err(|e| format!("Invalid recipient address '{}': {}", recipient, e))?;
+        builder = builder.to(to);
+    }
+    let email = builder.body(body).map_err(|e| format!("Failed to build notification email: {}", e))?;
+
+    let mut mailer_builder = SmtpTransport::starttls_relay(host)
+        .map_err(|e| format!("Failed to configure SMTP relay for {}: {}", host, e))?
+        .port(port);
+    if let (Some(username), Some(password)) = (username, password) {
+        mailer_builder = mailer_builder.credentials(Credentials::new(username.to_string(), password.to_string()));
+    }
+    let mailer = mailer_builder.build();
+
+    mailer.send(&email).map_err(|e| format!("Failed to send notification email via {}:{}: {}", host, port, e))?;
+    Ok(())
+}
+
+fn send_webhook(url: &str, repo_url: &str, error: &str) -> Result<(), String> {
+    let payload = WebhookPayload {
+        status: "failed",
+        repo_url,
+        timestamp: Local::now().to_rfc3339(),
+        error,
+    };
+
+    ureq::post(url)
+        .send_json(&payload)
+        .map_err(|e| format!("Webhook POST to {} failed: {}", url, e))?;
+    Ok(())
+}
+
+fn tail_of_log(log_file: &Path, n: usize) -> String {
+    let Ok(content) = std::fs::read_to_string(log_file) else {
+        return String::new();
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(n);
+    lines[start..].join("\n")
+}
@@ -0,0 +1,67 @@
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::repo_url::RepoUrl;
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabUser {
+    username: String,
+}
+
+/// Confirms `token` is a usable credential against the provider that hosts
+/// `repo_url` by calling its REST API, and returns the associated
+/// username (used for commit author identity) on success. Only github.com
+/// and gitlab.com are recognized; a self-hosted or unrecognized forge
+/// returns an error so the caller can fall back to `--skip-token-check`
+/// rather than silently skip validation.
+pub fn verify_token(repo_url: &str, token: &SecretString) -> Result<String, String> {
+    let host = RepoUrl::parse(repo_url)?.host;
+
+    match host.as_str() {
+        "github.com" => verify_github(token),
+        "gitlab.com" => verify_gitlab(token),
+        other => Err(format!(
+            "don't know how to validate a token against '{}'; rerun with --skip-token-check",
+            other
+        )),
+    }
+}
+
+fn verify_github(token: &SecretString) -> Result<String, String> {
+    let response = ureq::get("https://api.github.com/user")
+        .set("Authorization", &format!("Bearer {}", token.expose_secret()))
+        .set("User-Agent", "giterdone")
+        .call()
+        .map_err(|e| describe_error("GitHub", e))?;
+
+    response
+        .into_json::<GitHubUser>()
+        .map(|u| u.login)
+        .map_err(|e| format!("Failed to parse GitHub API response: {}", e))
+}
+
+fn verify_gitlab(token: &SecretString) -> Result<String, String> {
+    let response = ureq::get("https://gitlab.com/api/v4/user")
+        .set("PRIVATE-TOKEN", token.expose_secret())
+        .call()
+        .map_err(|e| describe_error("GitLab", e))?;
+
+    response
+        .into_json::<GitLabUser>()
+        .map(|u| u.username)
+        .map_err(|e| format!("Failed to parse GitLab API response: {}", e))
+}
+
+fn describe_error(provider: &str, err: ureq::Error) -> String {
+    match err {
+        ureq::Error::Status(401, _) => format!("{} rejected the token: expired or revoked", provider),
+        ureq::Error::Status(403, _) => format!("{} token has insufficient scope for this operation", provider),
+        ureq::Error::Status(code, _) => format!("{} API returned unexpected status {}", provider, code),
+        ureq::Error::Transport(t) => format!("Failed to reach {} API: {}", provider, t),
+    }
+}
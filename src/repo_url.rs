@@ -0,0 +1,63 @@
+use git_url_parse::{GitUrl, Scheme};
+
+/// A remote repository URL broken into the pieces needed to synthesize
+/// either an SSH or HTTPS form and to name the local clone directory,
+/// independent of which forge (github.com, gitlab.com, Bitbucket,
+/// self-hosted) or scheme the user originally supplied.
+pub struct RepoUrl {
+    pub host: String,
+    pub port: Option<u16>,
+    pub owner: Option<String>,
+    pub name: String,
+    /// Whether `repo_url` was written as `http(s)://...`. Relevant to
+    /// callers that only make sense for an SSH remote, e.g. known_hosts.
+    pub is_https: bool,
+}
+
+impl RepoUrl {
+    pub fn parse(repo_url: &str) -> Result<Self, String> {
+        let parsed = GitUrl::parse(repo_url)
+            .map_err(|e| format!("Failed to parse remote URL '{}': {}", repo_url, e))?;
+
+        let host = parsed
+            .host
+            .ok_or_else(|| format!("Remote URL '{}' has no host component", repo_url))?;
+
+        let is_https = matches!(parsed.scheme, Scheme::Https | Scheme::Http);
+
+        Ok(RepoUrl {
+            host,
+            port: parsed.port,
+            owner: parsed.owner,
+            name: parsed.name,
+            is_https,
+        })
+    }
+
+    /// Renders `git@host:owner/name` (or `git@host:name` if the URL had no
+    /// owner segment), the form OpenSSH expects for the scp-like syntax.
+    /// When the URL carried a port, which that syntax can't express, renders
+    /// `ssh://git@host:port/owner/name` instead.
+    pub fn to_ssh_url(&self) -> String {
+        let path = match &self.owner {
+            Some(owner) => format!("{}/{}", owner, self.name),
+            None => self.name.clone(),
+        };
+        match self.port {
+            Some(port) => format!("ssh://git@{}:{}/{}", self.host, port, path),
+            None => format!("git@{}:{}", self.host, path),
+        }
+    }
+
+    /// Renders `https://host[:port]/owner/name.git`.
+    pub fn to_https_url(&self) -> String {
+        let path = match &self.owner {
+            Some(owner) => format!("{}/{}", owner, self.name),
+            None => self.name.clone(),
+        };
+        match self.port {
+            Some(port) => format!("https://{}:{}/{}.git", self.host, port, path),
+            None => format!("https://{}/{}.git", self.host, path),
+        }
+    }
+}
@@ -1,43 +1,66 @@
+use crate::config::BackupGroup;
 use ignore::{WalkBuilder, DirEntry};
 use std::path::{Path, PathBuf};
 use std::fs;
 
 const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024; // 100MB
 
-pub fn scan(paths: &[PathBuf]) -> (Vec<(PathBuf, PathBuf)>, String) {
+/// A discovered file, tagged with the name of the group it was found
+/// under, so callers (e.g. the copy step's error messages) can tell which
+/// group a file came from.
+pub struct BackupFile {
+    pub source_path: PathBuf,
+    pub relative_path: PathBuf,
+    pub group: String,
+}
+
+pub fn scan(groups: &[(&str, &BackupGroup)]) -> (Vec<BackupFile>, String) {
     let mut files_to_backup = Vec::new();
-    let mut gitignore_patterns = Vec::new();
-
-    let single_root_mode = paths.len() == 1 && paths[0].is_dir();
-
-    for base_path in paths {
-        let walker = WalkBuilder::new(base_path)
-            .standard_filters(true) // Respect .gitignore, .ignore, etc.
-            .build();
-
-        for result in walker {
-            match result {
-                Ok(entry) => {
-                    if should_backup(&entry) {
-                        let relative_path = if single_root_mode {
-                            entry.path().strip_prefix(base_path).unwrap_or(entry.path()).to_path_buf()
-                        } else {
-                            entry.path().strip_prefix(base_path.parent().unwrap_or(base_path)).unwrap_or(entry.path()).to_path_buf()
-                        };
-                        files_to_backup.push((entry.path().to_path_buf(), relative_path));
-                    } else if entry.file_type().map_or(false, |ft| ft.is_file()) {
-                        // Add to .gitignore if it's a file that should be ignored
-                        if let Some(pattern) = path_to_gitignore_pattern(entry.path(), base_path) {
-                            gitignore_patterns.push(pattern);
+    let mut gitignore_sections = Vec::new();
+
+    for (group_name, group) in groups {
+        let mut gitignore_patterns = Vec::new();
+        let single_root_mode = group.paths.len() == 1 && group.paths[0].is_dir();
+
+        for base_path in &group.paths {
+            let walker = WalkBuilder::new(base_path)
+                .standard_filters(true) // Respect .gitignore, .ignore, etc.
+                .build();
+
+            for result in walker {
+                match result {
+                    Ok(entry) => {
+                        if should_backup(&entry) {
+                            let relative_path = if single_root_mode {
+                                entry.path().strip_prefix(base_path).unwrap_or(entry.path()).to_path_buf()
+                            } else {
+                                entry.path().strip_prefix(base_path.parent().unwrap_or(base_path)).unwrap_or(entry.path()).to_path_buf()
+                            };
+                            files_to_backup.push(BackupFile {
+                                source_path: entry.path().to_path_buf(),
+                                relative_path,
+                                group: group_name.to_string(),
+                            });
+                        } else if entry.file_type().map_or(false, |ft| ft.is_file()) {
+                            // Add to .gitignore if it's a file that should be ignored
+                            if let Some(pattern) = path_to_gitignore_pattern(entry.path(), base_path) {
+                                gitignore_patterns.push(pattern);
+                            }
                         }
                     }
+                    Err(err) => eprintln!("ERROR: {}", err),
                 }
-                Err(err) => eprintln!("ERROR: {}", err),
             }
         }
+
+        gitignore_patterns.extend(group.ignore.iter().cloned());
+
+        if !gitignore_patterns.is_empty() {
+            gitignore_sections.push(format!("# group: {}\n{}", group_name, gitignore_patterns.join("\n")));
+        }
     }
 
-    (files_to_backup, gitignore_patterns.join("\n"))
+    (files_to_backup, gitignore_sections.join("\n\n"))
 }
 
 fn should_backup(entry: &DirEntry) -> bool {
@@ -65,6 +88,14 @@ fn should_backup(entry: &DirEntry) -> bool {
     true
 }
 
+/// Applies the same junk/binary filtering `should_backup` uses during a
+/// full scan, but for a single path (e.g. a filesystem-watcher event)
+/// instead of a `DirEntry` from a walk. Does not check size, since a
+/// watcher event doesn't warrant a metadata read just to discard it later.
+pub fn is_backup_candidate(path: &Path) -> bool {
+    !is_binary(path) && !is_junk(path)
+}
+
 fn is_binary(path: &Path) -> bool {
     if let Ok(content) = fs::read(path) {
         // A simple heuristic: check for a significant number of non-UTF8 bytes.
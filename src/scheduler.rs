@@ -1,6 +1,23 @@
+use std::fs;
 use std::process::Command;
+
+use crate::config::Scheduler;
 use crate::logger::Logger;
 
+pub fn install(scheduler: Scheduler, schedule: &str, logger: &Logger) -> Result<(), String> {
+    match scheduler {
+        Scheduler::Cron => setup_cron_job(schedule, logger),
+        Scheduler::Systemd => setup_systemd_timer(schedule, logger),
+    }
+}
+
+pub fn uninstall(scheduler: Scheduler, logger: &Logger) -> Result<(), String> {
+    match scheduler {
+        Scheduler::Cron => remove_cron_job(logger),
+        Scheduler::Systemd => remove_systemd_timer(logger),
+    }
+}
+
 pub fn setup_cron_job(schedule: &str, logger: &Logger) -> Result<(), String> {
     let current_exe = std::env::current_exe().map_err(|e| format!("Failed to get current executable path: {}", e))?;
     let command_to_run = format!("{} --run-now", current_exe.to_str().unwrap());
@@ -45,3 +62,152 @@ pub fn setup_cron_job(schedule: &str, logger: &Logger) -> Result<(), String> {
     logger.log(&format!("Cron job set up with schedule: {}", schedule)).unwrap();
     Ok(())
 }
+
+fn remove_cron_job(logger: &Logger) -> Result<(), String> {
+    let current_crontab = Command::new("crontab")
+        .arg("-l")
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_else(|_| "".to_string());
+
+    let new_crontab: String = current_crontab
+        .lines()
+        .filter(|line| !line.contains("giterdone"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn crontab command: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        std::io::Write::write_all(&mut stdin, format!("{}\n", new_crontab).as_bytes())
+            .map_err(|e| format!("Failed to write to crontab stdin: {}", e))?;
+    } else {
+        return Err("Failed to get crontab stdin".to_string());
+    }
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for crontab command: {}", e))?;
+    if !status.success() {
+        return Err(format!("crontab command failed with status: {}", status));
+    }
+
+    logger.log("Cron job removed.").unwrap();
+    Ok(())
+}
+
+fn systemd_user_dir() -> Result<std::path::PathBuf, String> {
+    let config_dir = dirs::config_dir().ok_or_else(|| "Config directory not found".to_string())?;
+    Ok(config_dir.join("systemd").join("user"))
+}
+
+fn setup_systemd_timer(schedule: &str, logger: &Logger) -> Result<(), String> {
+    let current_exe = std::env::current_exe().map_err(|e| format!("Failed to get current executable path: {}", e))?;
+    let unit_dir = systemd_user_dir()?;
+    fs::create_dir_all(&unit_dir).map_err(|e| format!("Failed to create {:?}: {}", unit_dir, e))?;
+
+    let service_unit = format!(
+        "[Unit]\nDescription=giterdone backup\n\n[Service]\nType=oneshot\nExecStart={} --run-now\n",
+        current_exe.display()
+    );
+    fs::write(unit_dir.join("giterdone.service"), service_unit)
+        .map_err(|e| format!("Failed to write giterdone.service: {}", e))?;
+
+    let on_calendar = cron_to_oncalendar(schedule, logger);
+    let timer_unit = format!(
+        "[Unit]\nDescription=giterdone backup timer\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        on_calendar
+    );
+    fs::write(unit_dir.join("giterdone.timer"), timer_unit)
+        .map_err(|e| format!("Failed to write giterdone.timer: {}", e))?;
+
+    run_systemctl(&["daemon-reload"])?;
+    run_systemctl(&["enable", "--now", "giterdone.timer"])?;
+
+    logger.log(&format!("systemd timer installed with OnCalendar={}", on_calendar)).unwrap();
+    Ok(())
+}
+
+fn remove_systemd_timer(logger: &Logger) -> Result<(), String> {
+    run_systemctl(&["disable", "--now", "giterdone.timer"]).ok();
+
+    let unit_dir = systemd_user_dir()?;
+    fs::remove_file(unit_dir.join("giterdone.timer")).ok();
+    fs::remove_file(unit_dir.join("giterdone.service")).ok();
+
+    run_systemctl(&["daemon-reload"])?;
+    logger.log("systemd timer removed.").unwrap();
+    Ok(())
+}
+
+fn run_systemctl(args: &[&str]) -> Result<(), String> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .map_err(|e| format!("Failed to run systemctl {}: {}", args.join(" "), e))?;
+
+    if !status.success() {
+        return Err(format!("systemctl {} failed with status: {}", args.join(" "), status));
+    }
+    Ok(())
+}
+
+/// Translates a five-field cron schedule into a systemd `OnCalendar=`
+/// expression. Falls back to passing the input through when it doesn't
+/// look like five cron fields, so a user who already supplied systemd
+/// calendar syntax isn't mangled. The only change made on that path is
+/// dropping a leading `@`, which turns cron's `@hourly`/`@daily`/`@weekly`/
+/// `@monthly`/`@yearly` into the shorthands systemd itself understands.
+fn cron_to_oncalendar(schedule: &str, logger: &Logger) -> String {
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    if fields.len() != 5 {
+        return schedule.strip_prefix('@').unwrap_or(schedule).to_string();
+    }
+
+    let day_of_week = match cron_day_of_week(fields[4]) {
+        Some(day_of_week) => day_of_week,
+        None => {
+            logger.log(&format!(
+                "Warning: couldn't translate cron day-of-week field '{}' to OnCalendar= syntax; \
+                 passing the schedule through untouched rather than silently widening it to every day.",
+                fields[4]
+            )).unwrap();
+            return schedule.to_string();
+        }
+    };
+
+    let minute = cron_step_field(fields[0]);
+    let hour = cron_step_field(fields[1]);
+    let day_of_month = if fields[2] == "*" { "*".to_string() } else { fields[2].to_string() };
+    let month = if fields[3] == "*" { "*".to_string() } else { fields[3].to_string() };
+
+    let date_time = format!("*-{}-{} {}:{}:00", month, day_of_month, hour, minute);
+    if day_of_week == "*" {
+        date_time
+    } else {
+        format!("{} {}", day_of_week, date_time)
+    }
+}
+
+/// cron's `*/N` step syntax maps to systemd's `0/N`.
+fn cron_step_field(field: &str) -> String {
+    match field.strip_prefix("*/") {
+        Some(n) => format!("0/{}", n),
+        None => field.to_string(),
+    }
+}
+
+/// Translates a single numeric cron day-of-week value to its systemd
+/// weekday abbreviation. Returns `None` for anything else (a list, a
+/// range, a name) rather than guessing, since collapsing e.g. `1-5` to
+/// `*` would silently change a weekdays-only schedule to run every day.
+fn cron_day_of_week(field: &str) -> Option<String> {
+    const NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    if field == "*" {
+        return Some("*".to_string());
+    }
+    field.parse::<usize>().ok().and_then(|n| NAMES.get(n % 7)).map(|s| s.to_string())
+}
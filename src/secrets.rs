@@ -0,0 +1,182 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretString};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+use crate::logger::Logger;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The env var checked for the master passphrase on cron/systemd runs,
+/// where there is no tty to prompt for one.
+pub const PASSPHRASE_ENV_VAR: &str = "GITERDONE_PASSPHRASE";
+
+/// Writes the HTTPS personal access token to a `0600` file outside the
+/// (plaintext, serialized) `Config`, so it never ends up in `config.json`
+/// or gets echoed by `Status`. When `passphrase` is `Some`, the token is
+/// sealed with it first; otherwise it's written as plaintext, as before.
+pub fn store_token(token: &SecretString, passphrase: Option<&SecretString>) -> Result<(), String> {
+    let contents = match passphrase {
+        Some(passphrase) => seal(token, passphrase)?,
+        None => token.expose_secret().to_string(),
+    };
+    write_secret_file(&token_path()?, &contents)
+}
+
+pub fn load_token(passphrase: Option<&SecretString>) -> Result<SecretString, String> {
+    let contents = read_secret_file(&token_path()?)?;
+    match passphrase {
+        Some(passphrase) => unseal(&contents, passphrase),
+        None => Ok(SecretString::from(contents)),
+    }
+}
+
+/// Reads the master passphrase from `GITERDONE_PASSPHRASE`, for use during
+/// unattended cron/systemd runs where nothing can prompt interactively.
+pub fn passphrase_from_env() -> Option<SecretString> {
+    env::var(PASSPHRASE_ENV_VAR).ok().map(SecretString::from)
+}
+
+/// Resolves the master passphrase for decrypting sealed secrets:
+/// `GITERDONE_PASSPHRASE` first, falling back to an interactive prompt when
+/// stdin is a tty. Without this, a user running a command directly from a
+/// shell that doesn't have the env var exported would hit git2's opaque
+/// "exhausted configured credential methods" error instead of ever being
+/// asked for the passphrase they already set at `init` time.
+pub fn resolve_passphrase(logger: &Logger) -> Option<SecretString> {
+    if let Some(passphrase) = passphrase_from_env() {
+        return Some(passphrase);
+    }
+
+    if !io::stdin().is_terminal() {
+        logger.log(&format!(
+            "Secrets are encrypted but {} isn't set and there's no terminal to prompt on.",
+            PASSPHRASE_ENV_VAR
+        )).unwrap();
+        return None;
+    }
+
+    print!("Enter master passphrase to decrypt stored credentials: ");
+    if io::stdout().flush().is_err() {
+        return None;
+    }
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return None;
+    }
+    let passphrase = input.trim();
+    if passphrase.is_empty() {
+        None
+    } else {
+        Some(SecretString::from(passphrase.to_string()))
+    }
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase` via Argon2
+/// over a fresh random salt, then seals it with AES-256-GCM under a fresh
+/// random nonce. Returns `salt || nonce || ciphertext` (which includes the
+/// GCM tag), base64-encoded. The passphrase itself is never stored.
+pub fn seal(plaintext: &SecretString, passphrase: &SecretString) -> Result<String, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to initialize cipher: {}", e))?;
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext.expose_secret().as_bytes())
+        .map_err(|e| format!("Failed to encrypt secret: {}", e))?;
+
+    let mut sealed = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce_bytes);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(sealed))
+}
+
+/// Reverses `seal`. A wrong passphrase surfaces as an `Err` (AES-GCM
+/// authentication failure), never a panic.
+pub fn unseal(sealed_b64: &str, passphrase: &SecretString) -> Result<SecretString, String> {
+    let sealed = STANDARD
+        .decode(sealed_b64.trim())
+        .map_err(|e| format!("Failed to decode sealed secret: {}", e))?;
+
+    if sealed.len() < SALT_LEN + NONCE_LEN {
+        return Err("Sealed secret is truncated".to_string());
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = derive_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to initialize cipher: {}", e))?;
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    let plaintext = cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| "Failed to decrypt secret: wrong passphrase or corrupted data".to_string())?;
+
+    String::from_utf8(plaintext)
+        .map(SecretString::from)
+        .map_err(|e| format!("Decrypted secret is not valid UTF-8: {}", e))
+}
+
+fn derive_key(passphrase: &SecretString, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.expose_secret().as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key from passphrase: {}", e))?;
+    Ok(key)
+}
+
+fn write_secret_file(path: &PathBuf, contents: &str) -> Result<(), String> {
+    fs::create_dir_all(path.parent().unwrap()).map_err(|e| format!("Failed to create config dir: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open secret file {:?}: {}", path, e))?;
+
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write secret file {:?}: {}", path, e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)
+            .map_err(|e| format!("Failed to get secret file metadata: {}", e))?
+            .permissions();
+        permissions.set_mode(0o600);
+        fs::set_permissions(path, permissions)
+            .map_err(|e| format!("Failed to set secret file permissions: {}", e))?;
+    }
+
+    Ok(())
+}
+
+fn read_secret_file(path: &PathBuf) -> Result<String, String> {
+    let mut contents = String::new();
+    fs::File::open(path)
+        .map_err(|e| format!("Failed to open secret file {:?}: {}", path, e))?
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("Failed to read secret file {:?}: {}", path, e))?;
+    Ok(contents.trim().to_string())
+}
+
+fn token_path() -> Result<PathBuf, String> {
+    let config_dir = dirs::config_dir().ok_or_else(|| "Config directory not found".to_string())?;
+    Ok(config_dir.join("giterdone").join("token.secret"))
+}
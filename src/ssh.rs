@@ -1,54 +1,168 @@
 use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use rand::rngs::OsRng;
+use secrecy::{ExposeSecret, SecretString};
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
 use crate::logger::Logger;
+use crate::repo_url::RepoUrl;
+use crate::secrets;
 
-pub fn setup_ssh_key(key_content: &str, logger: &Logger) -> Result<PathBuf, String> {
-    let ssh_dir = dirs::home_dir()
+/// Where a freshly generated key lands when the user doesn't already have
+/// one; Ed25519 by default since there's no reason to default to RSA for a
+/// key we're generating ourselves.
+pub fn default_ssh_key_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
         .ok_or_else(|| "Could not find home directory".to_string())?
-        .join(".ssh");
-
-    fs::create_dir_all(&ssh_dir)
-        .map_err(|e| format!("Failed to create ~/.ssh directory: {}", e))?;
-
-    let key_path = ssh_dir.join("id_rsa"); // Default key path
+        .join(".ssh")
+        .join("id_ed25519"))
+}
 
+/// Writes caller-supplied private key bytes to `key_path`, as before, for
+/// users who already have a key they'd rather paste in.
+pub fn setup_ssh_key(key_path: &Path, key_content: &str, passphrase: Option<&SecretString>, logger: &Logger) -> Result<PathBuf, String> {
     if key_path.exists() {
         logger.log(&format!("Warning: SSH key already exists at {:?}. Overwriting.", key_path)).unwrap();
     }
+    store_private_key(key_path, key_content, passphrase)?;
+    logger.log(&format!("SSH key saved to {:?} with permissions 0o600.", key_path)).unwrap();
+    Ok(key_path.to_path_buf())
+}
+
+/// Generates a fresh Ed25519 keypair in-process (via the `ssh-key` crate)
+/// instead of requiring the user to produce one elsewhere, writes the
+/// private key in OpenSSH format to `key_path` (`0600`) and the public key
+/// to `key_path` + `.pub`, and returns the public key string so the caller
+/// can print "add this deploy key to your repo". The private key is always
+/// generated unencrypted and, if `passphrase` is given, sealed with
+/// `secrets::seal` the same way a pasted-in key is — not with `ssh-key`'s
+/// own OpenSSH-native encryption, since `load_ssh_key_content` (and the
+/// credential cascade behind it) only knows how to unseal our format.
+pub fn generate_ed25519_key(key_path: &Path, passphrase: Option<&SecretString>, logger: &Logger) -> Result<String, String> {
+    let private_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+        .map_err(|e| format!("Failed to generate Ed25519 keypair: {}", e))?;
+
+    let private_pem = private_key
+        .to_openssh(LineEnding::LF)
+        .map_err(|e| format!("Failed to encode private key: {}", e))?;
+    store_private_key(key_path, private_pem.as_str(), passphrase)?;
 
+    let public_key_str = private_key
+        .public_key()
+        .to_openssh()
+        .map_err(|e| format!("Failed to encode public key: {}", e))?;
+    let public_key_path = key_path.with_extension("pub");
+    fs::write(&public_key_path, format!("{}\n", public_key_str))
+        .map_err(|e| format!("Failed to write {:?}: {}", public_key_path, e))?;
+
+    logger.log(&format!("Generated Ed25519 keypair at {:?}", key_path)).unwrap();
+    Ok(public_key_str)
+}
+
+/// Writes an unencrypted OpenSSH private key PEM to `key_path`, sealing it
+/// with `secrets::seal` first when `passphrase` is given.
+fn store_private_key(key_path: &Path, private_pem: &str, passphrase: Option<&SecretString>) -> Result<(), String> {
+    let ssh_dir = key_path.parent().ok_or_else(|| "SSH key path has no parent directory".to_string())?;
+    fs::create_dir_all(ssh_dir).map_err(|e| format!("Failed to create {:?}: {}", ssh_dir, e))?;
+
+    let contents = match passphrase {
+        Some(passphrase) => secrets::seal(&SecretString::from(private_pem.to_string()), passphrase)?,
+        None => private_pem.to_string(),
+    };
+
+    write_key_file(key_path, &contents)
+}
+
+fn write_key_file(path: &Path, contents: &str) -> Result<(), String> {
     let mut file = OpenOptions::new()
         .create(true)
         .write(true)
-        .truncate(true) // Overwrite existing content
-        .open(&key_path)
-        .map_err(|e| format!("Failed to open SSH key file {:?}: {}", key_path, e))?;
+        .truncate(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open SSH key file {:?}: {}", path, e))?;
 
-    file.write_all(key_content.as_bytes())
-        .map_err(|e| format!("Failed to write SSH key to {:?}: {}", key_path, e))?;
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write SSH key to {:?}: {}", path, e))?;
 
-    // Set permissions to 0o600 (read/write for owner only)
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut permissions = fs::metadata(&key_path).map_err(|e| format!("Failed to get SSH key file metadata: {}", e))?.permissions();
+        let mut permissions = fs::metadata(path).map_err(|e| format!("Failed to get SSH key file metadata: {}", e))?.permissions();
         permissions.set_mode(0o600);
-        fs::set_permissions(&key_path, permissions).map_err(|e| format!("Failed to set SSH key file permissions: {}", e))?;
+        fs::set_permissions(path, permissions).map_err(|e| format!("Failed to set SSH key file permissions: {}", e))?;
     }
 
-    logger.log(&format!("SSH key saved to {:?} with permissions 0o600.", key_path)).unwrap();
-    Ok(key_path)
+    Ok(())
 }
 
-pub fn add_github_to_known_hosts(logger: &Logger) -> Result<(), String> {
-    let known_hosts_path = dirs::home_dir()
+/// Loads the private key's PEM content from `key_path`, unsealing it with
+/// `passphrase` if the key was stored encrypted. Used by the git2
+/// credential cascade, which needs the raw key bytes in memory rather than
+/// a path it can hand to OpenSSH.
+pub fn load_ssh_key_content(key_path: &Path, passphrase: Option<&SecretString>) -> Result<String, String> {
+    let contents = fs::read_to_string(key_path)
+        .map_err(|e| format!("Failed to read SSH key {:?}: {}", key_path, e))?;
+
+    match passphrase {
+        Some(passphrase) => Ok(secrets::unseal(&contents, passphrase)?.expose_secret().to_string()),
+        None => Ok(contents),
+    }
+}
+
+fn known_hosts_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
         .ok_or_else(|| "Could not find home directory".to_string())?
-        .join(".ssh").join("known_hosts");
+        .join(".ssh").join("known_hosts"))
+}
+
+/// The token ssh-keyscan/known_hosts use to identify a host: the bare
+/// hostname, or `[host]:port` when the port isn't the default 22.
+fn host_token(parsed: &RepoUrl) -> String {
+    match parsed.port {
+        Some(port) if port != 22 => format!("[{}]:{}", parsed.host, port),
+        _ => parsed.host.clone(),
+    }
+}
+
+/// Whether `repo_url`'s SSH host already has an entry in known_hosts.
+pub fn is_known_host_for_repo(repo_url: &str) -> bool {
+    let Ok(parsed) = RepoUrl::parse(repo_url) else { return false };
+    if parsed.is_https {
+        return false;
+    }
+    let Ok(path) = known_hosts_path() else { return false };
+    let Ok(existing) = fs::read_to_string(path) else { return false };
+    let token = host_token(&parsed);
+    existing
+        .lines()
+        .any(|line| line.split_whitespace().next().is_some_and(|t| t == token))
+}
 
-    let output = Command::new("ssh-keyscan")
-        .arg("github.com")
+/// Scans and appends the host key for `repo_url`'s SSH host (github.com,
+/// gitlab.com, a self-hosted Gitea, whatever) to `known_hosts`, rather than
+/// assuming github.com. Errors loudly if `repo_url` is an HTTPS URL, since
+/// known_hosts has nothing to do with that transport.
+pub fn add_known_host_for_repo(repo_url: &str, logger: &Logger) -> Result<(), String> {
+    let parsed = RepoUrl::parse(repo_url)?;
+    if parsed.is_https {
+        return Err(format!(
+            "'{}' is an HTTPS URL; known_hosts verification only applies to SSH remotes",
+            repo_url
+        ));
+    }
+
+    let known_hosts_path = known_hosts_path()?;
+    let token = host_token(&parsed);
+
+    let mut command = Command::new("ssh-keyscan");
+    if let Some(port) = parsed.port {
+        command.arg("-p").arg(port.to_string());
+    }
+    let output = command
+        .arg(&parsed.host)
         .output()
         .map_err(|e| format!("Failed to run ssh-keyscan: {}. Make sure it is installed and in your PATH.", e))?;
 
@@ -58,6 +172,26 @@ pub fn add_github_to_known_hosts(logger: &Logger) -> Result<(), String> {
                            String::from_utf8_lossy(&output.stdout)));
     }
 
+    let scanned = String::from_utf8_lossy(&output.stdout);
+    let existing = fs::read_to_string(&known_hosts_path).unwrap_or_default();
+    let new_lines: Vec<&str> = scanned
+        .lines()
+        .filter(|line| {
+            !line.is_empty()
+                && !existing.lines().any(|existing_line| {
+                    existing_line
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|existing_token| existing_token == token)
+                })
+        })
+        .collect();
+
+    if new_lines.is_empty() {
+        logger.log(&format!("{} already present in {:?}; nothing to add.", token, known_hosts_path)).unwrap();
+        return Ok(());
+    }
+
     let mut file = OpenOptions::new()
         .create(true)
         .write(true)
@@ -65,9 +199,11 @@ pub fn add_github_to_known_hosts(logger: &Logger) -> Result<(), String> {
         .open(&known_hosts_path)
         .map_err(|e| format!("Failed to open known_hosts file {:?}: {}", known_hosts_path, e))?;
 
-    file.write_all(&output.stdout)
-        .map_err(|e| format!("Failed to write to known_hosts file {:?}: {}", known_hosts_path, e))?;
+    for line in &new_lines {
+        writeln!(file, "{}", line)
+            .map_err(|e| format!("Failed to write to known_hosts file {:?}: {}", known_hosts_path, e))?;
+    }
 
-    logger.log(&format!("github.com added to {:?}.", known_hosts_path)).unwrap();
+    logger.log(&format!("{} added to {:?}.", token, known_hosts_path)).unwrap();
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,124 @@
+use crate::config::BackupGroup;
+use crate::scanner;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use notify::{RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Default quiet period when `Config::watch_debounce_secs` isn't set,
+/// chosen so an editor save-storm produces one commit instead of one per
+/// write.
+pub const DEFAULT_DEBOUNCE_SECS: u64 = 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A group's root path paired with the ignore rules that apply under it.
+struct WatchRoot {
+    root: PathBuf,
+    gitignore: Gitignore,
+}
+
+/// Watches every group's paths recursively and calls `on_change` once a
+/// burst of filesystem activity has been quiet for `debounce`. Events on
+/// files that `scanner::scan` would filter out anyway (junk, binary,
+/// `.gitignore`-excluded, hidden, or matching the group's own `ignore`
+/// patterns) are ignored so they can't wake the watcher. Runs until the
+/// watcher itself errors out.
+pub fn watch<F: FnMut()>(groups: &[(&str, &BackupGroup)], debounce: Duration, mut on_change: F) -> Result<(), String> {
+    let watch_roots: Vec<WatchRoot> = groups
+        .iter()
+        .flat_map(|(_, group)| group.paths.iter().map(|root| build_watch_root(root, &group.ignore)))
+        .collect();
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| format!("Failed to create filesystem watcher: {}", e))?;
+
+    for watch_root in &watch_roots {
+        watcher
+            .watch(&watch_root.root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {:?}: {}", watch_root.root, e))?;
+    }
+
+    let mut pending_since: Option<Instant> = None;
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if event.paths.iter().any(|p| is_relevant(p, &watch_roots)) {
+                    pending_since.get_or_insert_with(Instant::now);
+                }
+            }
+            Ok(Err(_)) => {
+                // Individual watch errors (e.g. a transient inotify hiccup)
+                // aren't fatal; keep watching.
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err("Filesystem watcher channel disconnected".to_string());
+            }
+        }
+
+        if let Some(since) = pending_since {
+            if since.elapsed() >= debounce {
+                pending_since = None;
+                on_change();
+            }
+        }
+    }
+}
+
+/// Builds the ignore matcher for a single group path: every `.gitignore`
+/// found under it (so e.g. a `.gitignore`-excluded `target/` or
+/// `node_modules/` can't wake the watcher, same as a real `scanner::scan`
+/// would skip them) plus the group's own `BackupGroup.ignore` patterns.
+/// Built once at watch startup, so a `.gitignore` added afterwards isn't
+/// picked up until the watcher restarts.
+fn build_watch_root(root: &Path, extra_patterns: &[String]) -> WatchRoot {
+    let base = if root.is_dir() { root.to_path_buf() } else { root.parent().unwrap_or(root).to_path_buf() };
+    let mut builder = GitignoreBuilder::new(&base);
+
+    if root.is_dir() {
+        for entry in WalkBuilder::new(root).hidden(false).build().flatten() {
+            if entry.file_name() == ".gitignore" {
+                builder.add(entry.path());
+            }
+        }
+    }
+    for pattern in extra_patterns {
+        let _ = builder.add_line(None, pattern);
+    }
+
+    let gitignore = builder.build().unwrap_or_else(|_| Gitignore::empty());
+    WatchRoot { root: root.to_path_buf(), gitignore }
+}
+
+fn is_relevant(path: &Path, watch_roots: &[WatchRoot]) -> bool {
+    if path.is_dir() {
+        return false;
+    }
+    if !scanner::is_backup_candidate(path) {
+        return false;
+    }
+
+    for watch_root in watch_roots {
+        if let Ok(relative) = path.strip_prefix(&watch_root.root) {
+            if is_hidden(relative) || watch_root.gitignore.matched(relative, false).is_ignore() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// `ignore::WalkBuilder`'s standard filters skip hidden files by default;
+/// mirrored here since a single changed-path check can't lean on the
+/// walker's own traversal to get that for free.
+fn is_hidden(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|c| c.as_os_str().to_str().is_some_and(|s| s.starts_with('.')))
+}